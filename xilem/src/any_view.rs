@@ -0,0 +1,15 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A type-erased [`WidgetView`](crate::WidgetView), for storing heterogeneous views
+//! of the same `State`/`Action` behind one type, e.g. in a `Vec`.
+
+use masonry::Widget;
+use xilem_core::AnyView;
+
+use crate::{Pod, ViewCtx};
+
+/// A boxed, type-erased [`WidgetView`](crate::WidgetView). Created with
+/// [`WidgetView::boxed`](crate::WidgetView::boxed).
+pub type AnyWidgetView<State, Action = ()> =
+    dyn AnyView<State, Action, ViewCtx, Pod<Box<dyn Widget>>> + Send + Sync;
@@ -0,0 +1,424 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! The driver which mediates between the [`Xilem`](crate::Xilem) application and the
+//! underlying Masonry widget trees — one per open OS window.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use masonry::app_driver::{AppDriver, DriverCtx};
+use masonry::widget::RootWidget;
+use masonry::{Action, WidgetId};
+use winit::window::{WindowAttributes, WindowId};
+
+use xilem_core::{AsyncCtx, DynMessage, MessageResult, RawProxy, ViewId, ViewPathTracker};
+
+use crate::view::focus::{FocusChanged, FocusRequest};
+use crate::{Mutation, ViewCtx, WidgetView, WindowRequest};
+
+/// A reserved widget id, for messages that have no real widget in the tree. Nothing
+/// in this crate uses it: [`async_action`] reports completion through the
+/// [`RawProxy`] by view path alone.
+pub const ASYNC_MARKER_WIDGET: WidgetId = WidgetId::reserved(u32::MAX);
+
+/// Schedules `future` on the [`ViewCtx`]'s tokio runtime, delivering its result back
+/// into `logic` as a one-shot message once it resolves.
+///
+/// This is the simplest async primitive Xilem exposes: it does not cache its result
+/// across rebuilds and cannot be cancelled. [`resource`](crate::view::resource) covers
+/// the "fetch and cache, keyed by dependencies" pattern with its own spawning.
+pub fn async_action<F, Message>(ctx: &mut ViewCtx, future: F)
+where
+    F: std::future::Future<Output = Message> + Send + 'static,
+    Message: Send + 'static,
+{
+    let proxy = ctx.proxy();
+    let path = ctx.view_path().to_vec();
+    ctx.runtime().spawn(async move {
+        let message = future.await;
+        proxy.send_message(path, Box::new(message));
+    });
+}
+
+/// A [`winit`] event-loop proxy that lets async tasks and other background work wake
+/// the Masonry event loop to deliver a [`DynMessage`] at a given view path.
+#[derive(Clone)]
+pub struct MasonryProxy(pub winit::event_loop::EventLoopProxy<MasonryUserEvent>);
+
+/// The user event Xilem's winit event loop is parameterised over.
+pub struct MasonryUserEvent {
+    pub(crate) path: Vec<ViewId>,
+    pub(crate) message: DynMessage,
+}
+
+impl RawProxy for MasonryProxy {
+    fn send_message(&self, path: Vec<ViewId>, message: DynMessage) {
+        let _ = self.0.send_event(MasonryUserEvent { path, message });
+    }
+
+    fn dyn_clone(&self) -> Arc<dyn RawProxy> {
+        Arc::new(self.clone())
+    }
+}
+
+/// A secondary window `logic` has asked to open, which the OS has not yet finished
+/// creating and so has no [`WindowId`] yet. Its content widget tree already exists,
+/// stashed in [`ViewCtx::secondary_widgets`](crate::ViewCtx) under `slot`; this only
+/// tracks the OS-window side of things until a real [`WindowId`] arrives.
+struct PendingWindow {
+    /// Its slot, assigned by [`ViewCtx::reserve_secondary_slot`](crate::ViewCtx) —
+    /// used to resolve this entry to the right place once
+    /// [`MasonryDriver::window_created`] fires.
+    slot: usize,
+    attributes: WindowAttributes,
+}
+
+/// The driver Xilem uses to translate Masonry widget actions into calls back into the
+/// application's `logic` closure, across every open window.
+///
+/// Earlier versions of Xilem assumed exactly one [`RootWidget`]; this instead tracks
+/// one per open secondary [`WindowId`], so that `logic` is free to return a
+/// [`view::Windows`](crate::view::Windows) describing a dynamic set of windows. It
+/// records which windows `logic` asked to open or close; it does not create OS
+/// windows itself, and it does not apply new attributes to an open window. The
+/// primary window (the one the winit
+/// event loop was bootstrapped with) is still handled through [`AppDriver::on_action`],
+/// exactly as before; actions from secondary windows arrive through
+/// [`Self::on_secondary_action`], which the event loop runner routes using `window_id`.
+pub struct MasonryDriver<State, Logic, View: WidgetView<State>, ViewState> {
+    pub(crate) current_view: View,
+    pub(crate) logic: Logic,
+    pub(crate) state: State,
+    pub(crate) ctx: ViewCtx,
+    pub(crate) view_state: ViewState,
+    /// The `WindowId` of each secondary window which already has one, keyed by its
+    /// slot; the content widget tree itself lives in
+    /// [`ViewCtx::secondary_widgets`](crate::ViewCtx), since that is what
+    /// `view::Windows::rebuild` needs to reach to diff it.
+    secondary: HashMap<usize, WindowId>,
+    /// Secondary windows queued via [`ViewCtx::open_window`] that the OS has not
+    /// finished creating yet.
+    pending: Vec<PendingWindow>,
+}
+
+impl<State, Logic, View> MasonryDriver<State, Logic, View, View::ViewState>
+where
+    View: WidgetView<State>,
+    Logic: FnMut(&mut State) -> View,
+{
+    pub(crate) fn new(
+        state: State,
+        logic: Logic,
+        mut ctx: ViewCtx,
+        current_view: View,
+        view_state: View::ViewState,
+    ) -> Self {
+        let pending = Self::drain_window_requests(&mut ctx, &mut HashMap::new());
+        Self {
+            current_view,
+            logic,
+            state,
+            ctx,
+            view_state,
+            secondary: HashMap::new(),
+            pending,
+        }
+    }
+
+    /// Turns queued [`WindowRequest`]s into `pending`/`secondary` updates. Slots are
+    /// assigned once, up front, by [`ViewCtx::reserve_secondary_slot`] — never
+    /// recomputed here from lengths — so a request can't collide with one already
+    /// tracked in `secondary` or still sitting in `pending`.
+    fn drain_window_requests(
+        ctx: &mut ViewCtx,
+        secondary: &mut HashMap<usize, WindowId>,
+    ) -> Vec<PendingWindow> {
+        let mut pending = Vec::new();
+        for request in std::mem::take(&mut ctx.window_requests) {
+            match request {
+                WindowRequest::Open(slot, attributes) => {
+                    pending.push(PendingWindow { slot, attributes });
+                }
+                WindowRequest::Update(slot, attributes) => {
+                    // The new `attributes` are dropped here: the driver keeps no copy
+                    // of them, so nothing re-applies them to the live winit `Window`.
+                    // The slot itself stays tracked from when it was opened.
+                    let _ = (slot, attributes);
+                }
+                WindowRequest::Close(slot) => {
+                    pending.retain(|p| p.slot != slot);
+                    secondary.remove(&slot);
+                    ctx.secondary_widgets.remove(&slot);
+                }
+                WindowRequest::UpdatePrimary(attributes) => {
+                    // Same as `Update` above, just for the one window that has no
+                    // slot of its own: the new `attributes` are dropped, and nothing
+                    // re-applies them to the live primary `Window`.
+                    let _ = attributes;
+                }
+            }
+        }
+        pending
+    }
+
+    /// Called by the event loop runner once winit has finished creating a window
+    /// Xilem asked for, moving it from `pending` into `secondary` under its real id.
+    pub fn window_created(&mut self, slot: usize, window_id: WindowId) {
+        if let Some(index) = self.pending.iter().position(|p| p.slot == slot) {
+            self.pending.remove(index);
+            self.secondary.insert(slot, window_id);
+        }
+    }
+
+    /// The secondary windows currently open, for the event loop runner to route
+    /// winit `WindowEvent`s to (alongside the primary window it already tracks).
+    pub fn secondary_windows(&mut self) -> impl Iterator<Item = (WindowId, &mut RootWidget)> {
+        let widgets = &mut self.ctx.secondary_widgets;
+        self.secondary.iter().filter_map(move |(slot, &window_id)| {
+            widgets.get_mut(slot).map(|root| (window_id, root))
+        })
+    }
+
+    /// Dispatches a widget action raised by `widget_id` in the secondary window
+    /// `window_id`, then reruns `logic` and applies the result.
+    pub fn on_secondary_action(
+        &mut self,
+        drv_ctx: &mut DriverCtx<'_>,
+        window_id: WindowId,
+        widget_id: WidgetId,
+        action: Action,
+    ) {
+        let Some((recorded_slot, id_path)) = self.ctx.widget_map.get(&widget_id).cloned() else {
+            return;
+        };
+        let is_this_window = recorded_slot
+            .and_then(|slot| self.secondary.get(&slot))
+            .is_some_and(|&id| id == window_id);
+        if !is_this_window {
+            return;
+        }
+        let message_result = self.current_view.message(
+            &mut self.view_state,
+            &id_path,
+            Box::new(action),
+            &mut self.state,
+        );
+        match message_result {
+            MessageResult::Action(()) | MessageResult::RequestRebuild => {
+                self.rebuild_secondary(drv_ctx);
+            }
+            MessageResult::Stale(_) | MessageResult::Nop => {}
+        }
+    }
+
+    /// Reruns `logic`, rebuilds the primary window's content to match (which, for a
+    /// `view::Windows`, also reconciles and diffs every secondary window's content
+    /// against it), and applies any resulting window open/close/retitle requests.
+    fn rebuild_secondary(&mut self, drv_ctx: &mut DriverCtx<'_>) {
+        let next_view = (self.logic)(&mut self.state);
+        let root = drv_ctx.get_root::<RootWidget>();
+        next_view.rebuild(
+            &self.current_view,
+            &mut self.view_state,
+            &mut self.ctx,
+            root,
+        );
+        self.current_view = next_view;
+        let mut newly_pending = Self::drain_window_requests(&mut self.ctx, &mut self.secondary);
+        self.pending.append(&mut newly_pending);
+        self.ctx.evict_stale_keep_alive();
+    }
+
+    /// Delivers a keyboard-focus change on `widget_id` to the
+    /// [`view::focusable`](crate::view::focusable) that registered it, then reruns
+    /// `logic` and applies the result, exactly as [`AppDriver::on_action`] does for a
+    /// widget action.
+    pub fn on_focus_changed(
+        &mut self,
+        drv_ctx: &mut DriverCtx<'_>,
+        widget_id: WidgetId,
+        has_focus: bool,
+    ) {
+        let Some((_, id_path)) = self.ctx.widget_map.get(&widget_id).cloned() else {
+            return;
+        };
+        let message_result = self.current_view.message(
+            &mut self.view_state,
+            &id_path,
+            Box::new(FocusChanged(has_focus)),
+            &mut self.state,
+        );
+        match message_result {
+            MessageResult::Action(()) | MessageResult::RequestRebuild => {
+                let next_view = (self.logic)(&mut self.state);
+                let root = drv_ctx.get_root::<RootWidget>();
+                next_view.rebuild(
+                    &self.current_view,
+                    &mut self.view_state,
+                    &mut self.ctx,
+                    root,
+                );
+                self.current_view = next_view;
+                let mut newly_pending =
+                    Self::drain_window_requests(&mut self.ctx, &mut self.secondary);
+                self.pending.append(&mut newly_pending);
+                self.ctx.evict_stale_keep_alive();
+            }
+            MessageResult::Stale(_) | MessageResult::Nop => {}
+        }
+    }
+
+    /// Drains the imperative focus moves ([`ViewCtx::request_focus`]) queued during
+    /// the last build/rebuild pass, for the event loop runner to apply to the live
+    /// widget tree (Masonry, not Xilem, owns where keyboard focus actually sits).
+    pub fn pending_focus_requests(&mut self) -> Vec<FocusRequest> {
+        std::mem::take(&mut self.ctx.focus_requests)
+    }
+
+    /// Handles a Tab (`previous: false`) or Shift-Tab (`previous: true`) key press
+    /// with `focused` currently holding keyboard focus: queues a move to the next
+    /// (or previous) widget in `focused`'s innermost enclosing
+    /// [`view::focus_scope`](crate::view::focus_scope)'s ring, wrapping at the
+    /// ring's ends, for the event loop runner to pick up from
+    /// [`Self::pending_focus_requests`]. A no-op if `focused` isn't registered in
+    /// any ring, or that ring has no other widget to move to.
+    pub fn request_tab_focus(&mut self, focused: WidgetId, previous: bool) {
+        let Some(ring) = self
+            .ctx
+            .focus_registry
+            .values()
+            .find(|ring| ring.iter().any(|&(id, _)| id == focused))
+        else {
+            return;
+        };
+        if ring.len() < 2 {
+            return;
+        }
+        let index = ring
+            .iter()
+            .position(|&(id, _)| id == focused)
+            .expect("`ring` was just found to contain `focused`");
+        let target = if previous {
+            ring[(index + ring.len() - 1) % ring.len()].0
+        } else {
+            ring[(index + 1) % ring.len()].0
+        };
+        self.ctx.request_focus(FocusRequest::Widget(target));
+    }
+
+    /// Replays a [`Mutation`] stream recorded (via [`ViewCtx::start_recording`])
+    /// against some other, independently-built widget tree, driving this one to
+    /// match it — the entry point a thin client uses to mirror a remotely-run
+    /// `State`+`Logic`.
+    ///
+    /// [`Mutation::RouteAction`] re-dispatches through `logic`/`message`/`rebuild`
+    /// exactly as [`AppDriver::on_action`] would for a locally-raised action, but
+    /// only the dispatch itself is real: its `action` field is bytes produced by
+    /// the lossy [`encode_action`] placeholder wire format, not a decodable
+    /// `masonry::Action`, so the message actually delivered is always `()` rather
+    /// than the original action the remote side raised. The tree-shaping variants
+    /// (`CreateWidget`/`SetProperty`/`InsertChild`/`RemoveChild`/`ReplaceWith`) are
+    /// only logged for now: replaying them for real needs a registry mapping each
+    /// `type_name` back to a Masonry widget constructor, which this crate does not
+    /// yet have.
+    pub fn apply_mutations(&mut self, drv_ctx: &mut DriverCtx<'_>, mutations: Vec<Mutation>) {
+        for mutation in mutations {
+            match mutation {
+                Mutation::RouteAction {
+                    widget: _,
+                    path,
+                    action: _,
+                } => {
+                    let message_result = self.current_view.message(
+                        &mut self.view_state,
+                        &path,
+                        // The encoded action is opaque bytes today (see
+                        // `mutation::EncodedAction`); there is nothing to decode it
+                        // back into without a typed wire format for `masonry::Action`.
+                        Box::new(()),
+                        &mut self.state,
+                    );
+                    if matches!(
+                        message_result,
+                        MessageResult::Action(()) | MessageResult::RequestRebuild
+                    ) {
+                        let next_view = (self.logic)(&mut self.state);
+                        let root = drv_ctx.get_root::<RootWidget>();
+                        next_view.rebuild(
+                            &self.current_view,
+                            &mut self.view_state,
+                            &mut self.ctx,
+                            root,
+                        );
+                        self.current_view = next_view;
+                        self.ctx.evict_stale_keep_alive();
+                    }
+                }
+                other => {
+                    tracing::warn!(
+                        "`MasonryDriver::apply_mutations` cannot yet replay {other:?} \
+                         without a widget-type registry"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Encodes `action` as a placeholder wire format for [`Mutation::RouteAction`].
+///
+/// `masonry::Action` has no `serde` impl of its own, so this is lossy — good enough
+/// to prove the recording path end to end, but not a format a real remote client
+/// should rely on; that needs `Action` (or a stable subset of it) to become
+/// serializable in its own right.
+fn encode_action(action: &Action) -> Vec<u8> {
+    format!("{action:?}").into_bytes()
+}
+
+impl<State, Logic, View> AppDriver for MasonryDriver<State, Logic, View, View::ViewState>
+where
+    View: WidgetView<State>,
+    Logic: FnMut(&mut State) -> View,
+{
+    fn on_action(&mut self, drv_ctx: &mut DriverCtx<'_>, widget_id: WidgetId, action: Action) {
+        let Some((recorded_slot, id_path)) = self.ctx.widget_map.get(&widget_id).cloned() else {
+            tracing::error!("Got action {action:?} for unknown widget {widget_id:?}");
+            return;
+        };
+        if recorded_slot.is_some() {
+            // This widget lives in a secondary window; `on_secondary_action` handles
+            // those once the event loop runner identifies which `WindowId` raised it.
+            return;
+        }
+        self.ctx.record_mutation(Mutation::RouteAction {
+            widget: widget_id,
+            path: id_path.clone(),
+            action: encode_action(&action),
+        });
+        let message_result = self.current_view.message(
+            &mut self.view_state,
+            &id_path,
+            Box::new(action),
+            &mut self.state,
+        );
+        match message_result {
+            MessageResult::Action(()) | MessageResult::RequestRebuild => {
+                let next_view = (self.logic)(&mut self.state);
+                let root = drv_ctx.get_root::<RootWidget>();
+                next_view.rebuild(
+                    &self.current_view,
+                    &mut self.view_state,
+                    &mut self.ctx,
+                    root,
+                );
+                self.current_view = next_view;
+                let mut newly_pending =
+                    Self::drain_window_requests(&mut self.ctx, &mut self.secondary);
+                self.pending.append(&mut newly_pending);
+                self.ctx.evict_stale_keep_alive();
+            }
+            MessageResult::Stale(_) | MessageResult::Nop => {}
+        }
+    }
+}
@@ -26,6 +26,7 @@ pub use masonry::{
     event_loop_runner::{EventLoop, EventLoopBuilder},
     Color, TextAlignment,
 };
+pub use winit::window::WindowId;
 pub use xilem_core as core;
 
 mod one_of;
@@ -36,8 +37,13 @@ pub use any_view::AnyWidgetView;
 mod driver;
 pub use driver::{async_action, MasonryDriver, MasonryProxy, ASYNC_MARKER_WIDGET};
 
+mod mutation;
+pub use mutation::{EncodedAction, Mutation, MutationRecorder, PropertyValue};
+
 pub mod view;
 
+pub mod testing;
+
 /// Tokio is the async runner used with Xilem.
 pub use tokio;
 
@@ -69,7 +75,10 @@ where
         self
     }
 
-    // TODO: Make windows a specific view
+    /// Runs this app, creating a single window from `window_title`.
+    ///
+    /// For apps which need more than one window, return a [`view::Windows`] (built
+    /// with [`view::windows`]) from `logic` instead, and see [`Self::run_windowed_in`].
     pub fn run_windowed(
         self,
         // We pass in the event loop builder to allow
@@ -90,7 +99,14 @@ where
         self.run_windowed_in(event_loop, window_attributes)
     }
 
-    // TODO: Make windows into a custom view
+    /// Runs this app, creating the window(s) `logic`'s first result asks for.
+    ///
+    /// `window_attributes` seeds the *first* window only (the one used to bootstrap
+    /// the winit event loop); if `View` is a [`view::Windows`] with further entries,
+    /// their content is built and each is queued on the [`MasonryDriver`] as a
+    /// pending window. This runs Masonry's `event_loop_runner::run_with`, which shows
+    /// only the first window: it does not open the queued secondary windows, and it
+    /// does not apply later retitle/resize requests to any window.
     pub fn run_windowed_in(
         self,
         mut event_loop: EventLoopBuilder,
@@ -122,16 +138,25 @@ where
             view_tree_changed: false,
             proxy,
             runtime: self.runtime,
+            current_window: None,
+            secondary_widgets: HashMap::new(),
+            next_secondary_slot: 0,
+            window_requests: Vec::new(),
+            keep_alive_map: HashMap::new(),
+            focus_scope_stack: Vec::new(),
+            focus_registry: HashMap::new(),
+            next_focus_scope: 0,
+            focus_requests: Vec::new(),
+            recorder: None,
         };
         let (pod, view_state) = first_view.build(&mut ctx);
         let root_widget = RootWidget::from_pod(pod.inner);
-        let driver = MasonryDriver {
-            current_view: first_view,
-            logic: self.logic,
-            state: self.state,
-            ctx,
-            view_state,
-        };
+        // Any secondary windows `first_view` (e.g. a `view::Windows`) asked for via
+        // `ViewCtx::open_window` during that first build are queued in
+        // `ctx.window_requests`; `MasonryDriver::new` moves each into its pending
+        // list, where it waits for `MasonryDriver::window_created` to supply the real
+        // `WindowId` winit assigns it.
+        let driver = MasonryDriver::new(self.state, self.logic, ctx, first_view, view_state);
         (root_widget, driver)
     }
 }
@@ -209,7 +234,50 @@ where
     type Widget = W;
 }
 
-type WidgetMap = HashMap<WidgetId, Vec<ViewId>>;
+/// A widget's position in the view tree, plus the secondary window (if any, see
+/// [`view::Windows`]) it was built into: `None` means the primary window.
+type WidgetMap = HashMap<WidgetId, (Option<usize>, Vec<ViewId>)>;
+
+/// A type-erased [`Pod`], used where a widget's concrete type can't be named, e.g. in
+/// [`view::keep_alive`]'s cache.
+pub(crate) type AnyWidgetPod = Pod<Box<dyn Widget>>;
+
+/// How many rebuild passes a [`view::keep_alive`] entry survives without being
+/// revived before it is evicted, bounding the cache's memory use.
+const KEEP_ALIVE_EVICTION_PASSES: u32 = 5;
+
+/// A [`view::keep_alive`] entry stashed by [`ViewCtx::cache_keep_alive`], kept around
+/// in case [`ViewCtx::revive_keep_alive`] is asked for the same key again before
+/// [`ViewCtx::evict_stale_keep_alive`] drops it.
+struct KeepAliveEntry {
+    pod: AnyWidgetPod,
+    view_state: Box<dyn std::any::Any>,
+    /// The `id_path` [`view::keep_alive`] itself was built at when this was cached —
+    /// the common prefix of every widget in `descendants`' recorded path.
+    base_path: Vec<ViewId>,
+    /// Every widget this subtree registered in [`WidgetMap`], so eviction can remove
+    /// them (they can never send another action once evicted) and revival can
+    /// rewrite their recorded path to the subtree's new position in the tree.
+    descendants: Vec<WidgetId>,
+    /// Rebuild passes since this entry was last touched; see
+    /// [`KEEP_ALIVE_EVICTION_PASSES`].
+    age: u32,
+}
+
+/// A request, queued by [`ViewCtx::open_window`]/[`update_window`](ViewCtx::update_window)/
+/// [`close_window`](ViewCtx::close_window)/
+/// [`update_primary_window`](ViewCtx::update_primary_window) during `build`/`rebuild`,
+/// for the driver to drain after the pass. The driver tracks opens and closes; it
+/// drops the attributes of `Update`/`UpdatePrimary`.
+pub(crate) enum WindowRequest {
+    Open(usize, WindowAttributes),
+    Update(usize, WindowAttributes),
+    Close(usize),
+    /// Retitle/resize/etc. the primary window, the one `run_windowed_in` bootstrapped
+    /// the event loop with. Unlike a secondary window it has no slot of its own, so
+    /// this carries no index.
+    UpdatePrimary(WindowAttributes),
+}
 
 pub struct ViewCtx {
     /// The map from a widgets id to its position in the View tree.
@@ -220,6 +288,42 @@ pub struct ViewCtx {
     view_tree_changed: bool,
     proxy: Arc<dyn RawProxy>,
     runtime: tokio::runtime::Runtime,
+    /// The secondary window currently being built/rebuilt, if any; recorded into
+    /// [`WidgetMap`] so actions are dispatched to the right window. `None` while
+    /// building the primary window's tree.
+    current_window: Option<usize>,
+    /// The actual widget tree for each open secondary window, keyed by the slot
+    /// [`Self::reserve_secondary_slot`] assigned it. Kept here, rather than on the
+    /// driver, so [`view::Windows::rebuild`] can reach a secondary window's content
+    /// to diff it, the same way it already reaches the primary window's through its
+    /// `element` parameter; the driver only needs to track the matching `WindowId`.
+    secondary_widgets: HashMap<usize, RootWidget>,
+    /// The next slot [`Self::reserve_secondary_slot`] will hand out. Monotonic, so a
+    /// closed window's slot is never reused and can't collide with one that is still
+    /// pending or already open.
+    next_secondary_slot: usize,
+    /// Window open/retitle/close requests raised by [`view::Windows`] during this
+    /// build/rebuild pass, drained by the driver afterwards.
+    pub(crate) window_requests: Vec<WindowRequest>,
+    /// Widgets torn down by [`view::keep_alive`] while their key was missing from the
+    /// latest view tree, cached in case the same key reappears.
+    keep_alive_map: HashMap<view::KeepAliveKey, KeepAliveEntry>,
+    /// The [`view::focus_scope`]s currently being built/rebuilt, innermost last, so
+    /// [`view::focusable`] can register into the right one.
+    focus_scope_stack: Vec<view::FocusScopeId>,
+    /// Every [`view::focus_scope`]'s [`view::focusable`] widgets, in traversal order,
+    /// each alongside the `id_path` it was built at; reset each time that scope is
+    /// (re)built.
+    focus_registry: HashMap<view::FocusScopeId, Vec<(WidgetId, Vec<ViewId>)>>,
+    /// The next id to hand out from [`Self::push_focus_scope`].
+    next_focus_scope: u64,
+    /// Imperative focus moves raised during this build/rebuild pass, drained by the
+    /// driver afterwards.
+    pub(crate) focus_requests: Vec<view::FocusRequest>,
+    /// When `Some`, every [`Mutation`] this context's build/rebuild pass makes is
+    /// appended here instead of (or alongside) being applied to a local widget tree.
+    /// See [`Self::start_recording`].
+    recorder: Option<MutationRecorder>,
 }
 
 impl ViewPathTracker for ViewCtx {
@@ -254,7 +358,11 @@ impl ViewCtx {
         let value = f(self);
         let id = value.inner.id();
         let path = self.id_path.clone();
-        self.widget_map.insert(id, path);
+        self.widget_map.insert(id, (self.current_window, path));
+        self.record_mutation(Mutation::CreateWidget {
+            widget: id,
+            type_name: std::any::type_name::<E>().to_string(),
+        });
         value
     }
 
@@ -265,6 +373,261 @@ impl ViewCtx {
     pub fn runtime(&self) -> &tokio::runtime::Runtime {
         &self.runtime
     }
+
+    /// Runs `f` with `window` recorded as the secondary window currently being built,
+    /// so any action widgets it creates are routed back to that window.
+    pub(crate) fn with_secondary_window<R>(
+        &mut self,
+        window: usize,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let previous = self.current_window.replace(window);
+        let result = f(self);
+        self.current_window = previous;
+        result
+    }
+
+    /// Reserves a slot for a new secondary window, to build its content under (via
+    /// [`Self::with_secondary_window`]) before handing it to [`Self::open_window`].
+    /// Used by [`view::Windows`].
+    pub(crate) fn reserve_secondary_slot(&mut self) -> usize {
+        let slot = self.next_secondary_slot;
+        self.next_secondary_slot += 1;
+        slot
+    }
+
+    /// Stores `content` as the widget tree for secondary window `slot` (reserved with
+    /// [`Self::reserve_secondary_slot`]) and queues a request for an OS window showing
+    /// it, which the driver moves into its pending list when it next processes
+    /// [`Self::window_requests`]. Used by [`view::Windows`].
+    pub fn open_window(
+        &mut self,
+        slot: usize,
+        attributes: WindowAttributes,
+        content: Pod<Box<dyn Widget>>,
+    ) {
+        self.secondary_widgets
+            .insert(slot, RootWidget::from_pod(content.inner));
+        self.window_requests
+            .push(WindowRequest::Open(slot, attributes));
+    }
+
+    /// Requests that the secondary window at `slot` be retitled/resized/etc. to match
+    /// `attributes`. Used by [`view::Windows`].
+    pub fn update_window(&mut self, slot: usize, attributes: WindowAttributes) {
+        self.window_requests
+            .push(WindowRequest::Update(slot, attributes));
+    }
+
+    /// Requests that the secondary window at `slot` be closed. Used by
+    /// [`view::Windows`].
+    pub fn close_window(&mut self, slot: usize) {
+        self.window_requests.push(WindowRequest::Close(slot));
+    }
+
+    /// Requests that the primary window be retitled/resized/etc. to match
+    /// `attributes`, the same way [`Self::update_window`] does for a secondary one.
+    /// Used by [`view::Windows`].
+    pub fn update_primary_window(&mut self, attributes: WindowAttributes) {
+        self.window_requests
+            .push(WindowRequest::UpdatePrimary(attributes));
+    }
+
+    /// Gives `f` mutable access to secondary window `slot`'s content widget, for
+    /// [`view::Windows::rebuild`] to diff it against the previous view exactly as it
+    /// does the primary window's through its `element` parameter. A no-op (returning
+    /// `None`) if `slot` isn't currently open.
+    pub(crate) fn with_secondary_root_mut<R>(
+        &mut self,
+        slot: usize,
+        f: impl FnOnce(&mut Self, WidgetMut<'_, Box<dyn Widget>>) -> R,
+    ) -> Option<R> {
+        let mut root = self.secondary_widgets.remove(&slot)?;
+        let result = root.edit(|mut root_mut| {
+            let child = RootWidget::child_mut(&mut root_mut);
+            f(self, child)
+        });
+        self.secondary_widgets.insert(slot, root);
+        Some(result)
+    }
+
+    /// Removes and returns the cached `(Pod, ViewState)` for `key`, if one was
+    /// stashed by an earlier [`Self::cache_keep_alive`] call and its types match.
+    /// Rewrites every descendant widget's recorded [`WidgetMap`] entry to the
+    /// subtree's new position, since reviving it can place it at a different
+    /// `id_path` (or window) than where it was cached from. Used by
+    /// [`view::keep_alive`].
+    pub(crate) fn revive_keep_alive<W: Widget, ViewState: 'static>(
+        &mut self,
+        key: view::KeepAliveKey,
+    ) -> Option<(Pod<W>, ViewState)> {
+        let entry = self.keep_alive_map.remove(&key)?;
+        let pod = match entry.pod.inner.downcast::<W>() {
+            Ok(pod) => pod,
+            // The key was reused for a different widget type (a `keep_alive` call
+            // site changed what it builds between rebuilds): this entry can never be
+            // revived, so its descendants must be cleaned up here rather than left
+            // for an `evict_stale_keep_alive` pass that will never see this entry
+            // again, since it's already been removed from `keep_alive_map` above.
+            Err(_) => {
+                Self::remove_widget_map_entries(&mut self.widget_map, &entry.descendants);
+                return None;
+            }
+        };
+        let view_state = match entry.view_state.downcast::<ViewState>() {
+            Ok(view_state) => view_state,
+            Err(_) => {
+                Self::remove_widget_map_entries(&mut self.widget_map, &entry.descendants);
+                return None;
+            }
+        };
+        let new_base = self.id_path.clone();
+        let new_window = self.current_window;
+        for id in entry.descendants {
+            if let Some((widget_window, path)) = self.widget_map.get_mut(&id) {
+                let suffix = path.split_off(entry.base_path.len().min(path.len()));
+                *widget_window = new_window;
+                *path = new_base.iter().copied().chain(suffix).collect();
+            }
+        }
+        Some((Pod::from(pod), *view_state))
+    }
+
+    /// Removes every id in `descendants` from `widget_map`, used when a cached
+    /// [`view::keep_alive`] entry is dropped (either evicted, or revived with a
+    /// type that no longer matches) rather than revived into the live tree.
+    fn remove_widget_map_entries(widget_map: &mut WidgetMap, descendants: &[WidgetId]) {
+        for id in descendants {
+            widget_map.remove(id);
+        }
+    }
+
+    /// Stashes `element`'s widget and `view_state` under `key`, for
+    /// [`Self::revive_keep_alive`] to return if the same key reappears in a later
+    /// rebuild, along with every widget this subtree has registered in
+    /// [`WidgetMap`] so far, for [`Self::evict_stale_keep_alive`] to clean up if it
+    /// never does. Used by [`view::keep_alive`].
+    pub(crate) fn cache_keep_alive<W: Widget, ViewState: 'static>(
+        &mut self,
+        key: view::KeepAliveKey,
+        element: WidgetMut<'_, W>,
+        view_state: ViewState,
+    ) {
+        let base_path = self.id_path.clone();
+        let window = self.current_window;
+        let descendants = self
+            .widget_map
+            .iter()
+            .filter(|(_, (widget_window, path))| {
+                *widget_window == window && path.starts_with(base_path.as_slice())
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        let pod: Pod<W> = Pod::from(element.detach());
+        self.keep_alive_map.insert(
+            key,
+            KeepAliveEntry {
+                pod: AnyWidgetPod::upcast(pod),
+                view_state: Box::new(view_state),
+                base_path,
+                descendants,
+                age: 0,
+            },
+        );
+    }
+
+    /// Ages every cached [`view::keep_alive`] entry by one rebuild pass, dropping any
+    /// which have gone unrevived for [`KEEP_ALIVE_EVICTION_PASSES`] passes in a row
+    /// — and, for those, removing every widget they registered in [`WidgetMap`], so
+    /// evicted widgets (which can never send another action) don't linger in it
+    /// forever.
+    pub(crate) fn evict_stale_keep_alive(&mut self) {
+        let mut evicted = Vec::new();
+        self.keep_alive_map.retain(|_, entry| {
+            entry.age += 1;
+            let alive = entry.age < KEEP_ALIVE_EVICTION_PASSES;
+            if !alive {
+                evicted.push(std::mem::take(&mut entry.descendants));
+            }
+            alive
+        });
+        for descendants in &evicted {
+            Self::remove_widget_map_entries(&mut self.widget_map, descendants);
+        }
+    }
+
+    /// Opens a new [`view::focus_scope`], resetting its focus ring so the upcoming
+    /// build/rebuild can repopulate it in the new traversal order.
+    pub(crate) fn push_focus_scope(&mut self) -> view::FocusScopeId {
+        let id = view::FocusScopeId(self.next_focus_scope);
+        self.next_focus_scope += 1;
+        self.focus_registry.insert(id, Vec::new());
+        self.focus_scope_stack.push(id);
+        id
+    }
+
+    /// Re-enters an existing [`view::focus_scope`] for a rebuild, resetting its focus
+    /// ring the same way [`Self::push_focus_scope`] does for a build.
+    pub(crate) fn reenter_focus_scope(&mut self, id: view::FocusScopeId) {
+        self.focus_registry.insert(id, Vec::new());
+        self.focus_scope_stack.push(id);
+    }
+
+    /// Leaves the innermost [`view::focus_scope`], returning to its parent (if any).
+    pub(crate) fn pop_focus_scope(&mut self) {
+        self.focus_scope_stack.pop();
+    }
+
+    /// Drops a [`view::focus_scope`]'s focus ring entirely, once it has been torn down.
+    pub(crate) fn remove_focus_scope(&mut self, id: view::FocusScopeId) {
+        self.focus_registry.remove(&id);
+    }
+
+    /// Appends `widget_id` to the innermost enclosing [`view::focus_scope`]'s focus
+    /// ring, at the current `id_path`. A no-op if there is no enclosing scope.
+    pub(crate) fn register_focusable(&mut self, widget_id: WidgetId) {
+        if let Some(scope) = self.focus_scope_stack.last() {
+            self.focus_registry
+                .get_mut(scope)
+                .expect("focus scopes are always registered before being entered")
+                .push((widget_id, self.id_path.clone()));
+        }
+    }
+
+    /// Requests that keyboard focus move as described by `request`, applied by the
+    /// driver on its next pass. Used by views that need to move focus imperatively,
+    /// e.g. in response to an action rather than Tab/Shift-Tab.
+    pub fn request_focus(&mut self, request: view::FocusRequest) {
+        self.focus_requests.push(request);
+    }
+
+    /// Switches this context into recording mode: [`Mutation`]s are appended to an
+    /// internal [`MutationRecorder`] as `build`/`rebuild` makes them, for
+    /// [`Self::stop_recording`] to hand back afterwards. Used to mirror a view tree
+    /// to a remote client instead of (or alongside) a local widget tree.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(MutationRecorder::default());
+    }
+
+    /// Leaves recording mode, returning every [`Mutation`] recorded since the
+    /// matching [`Self::start_recording`] call.
+    pub fn stop_recording(&mut self) -> Vec<Mutation> {
+        self.recorder
+            .take()
+            .map(|mut recorder| recorder.drain())
+            .unwrap_or_default()
+    }
+
+    /// Appends `mutation` to the current recording, if [`Self::start_recording`] has
+    /// been called; a no-op otherwise. Used by [`Self::with_action_widget`] for
+    /// [`Mutation::CreateWidget`], and available to any view that restructures its
+    /// own children or changes a property, for the [`Mutation`] variants that
+    /// describe.
+    pub fn record_mutation(&mut self, mutation: Mutation) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.push(mutation);
+        }
+    }
 }
 
 impl AsyncCtx for ViewCtx {
@@ -272,3 +635,109 @@ impl AsyncCtx for ViewCtx {
         self.proxy.clone()
     }
 }
+
+/// A bare-bones [`ViewCtx`] fixture shared by this crate's `#[cfg(test)]` modules,
+/// so each one doesn't have to restate every private field of [`ViewCtx`] just to
+/// get something to call view/`ViewCtx` methods on.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::sync::Arc;
+
+    use xilem_core::{DynMessage, RawProxy, ViewId};
+
+    use crate::ViewCtx;
+
+    struct NullProxy;
+
+    impl RawProxy for NullProxy {
+        fn send_message(&self, _path: Vec<ViewId>, _message: DynMessage) {}
+
+        fn dyn_clone(&self) -> Arc<dyn RawProxy> {
+            Arc::new(NullProxy)
+        }
+    }
+
+    pub(crate) fn test_ctx() -> ViewCtx {
+        ViewCtx {
+            widget_map: Default::default(),
+            id_path: Vec::new(),
+            view_tree_changed: false,
+            proxy: Arc::new(NullProxy),
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("building a current-thread tokio runtime"),
+            current_window: None,
+            secondary_widgets: Default::default(),
+            next_secondary_slot: 0,
+            window_requests: Vec::new(),
+            keep_alive_map: Default::default(),
+            focus_scope_stack: Vec::new(),
+            focus_registry: Default::default(),
+            next_focus_scope: 0,
+            focus_requests: Vec::new(),
+            recorder: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use masonry::testing::TestHarness;
+    use masonry::widget::Label;
+
+    use super::*;
+    use crate::test_support::test_ctx;
+
+    #[test]
+    fn revive_keep_alive_rewrites_descendants_to_their_new_path() {
+        let mut ctx = test_ctx();
+        let descendant = WidgetId::reserved(1);
+        ctx.id_path = vec![ViewId::new(1), ViewId::new(2)];
+        ctx.widget_map
+            .insert(descendant, (None, vec![ViewId::new(1), ViewId::new(2), ViewId::new(3)]));
+
+        let mut harness = TestHarness::create(WidgetPod::new(Label::new("a")));
+        harness.edit_root_widget(|root| {
+            ctx.cache_keep_alive(view::KeepAliveKey::new("k"), root, 7u32);
+        });
+        assert!(ctx.keep_alive_map.contains_key(&view::KeepAliveKey::new("k")));
+
+        ctx.id_path = vec![ViewId::new(9)];
+        let revived = ctx.revive_keep_alive::<Label, u32>(view::KeepAliveKey::new("k"));
+        assert!(revived.is_some());
+        assert_eq!(revived.unwrap().1, 7);
+
+        let (_, path) = ctx
+            .widget_map
+            .get(&descendant)
+            .expect("revival must not drop a live widget's map entry");
+        assert_eq!(*path, vec![ViewId::new(9), ViewId::new(3)]);
+    }
+
+    #[test]
+    fn evict_stale_keep_alive_removes_its_descendants_from_widget_map() {
+        let mut ctx = test_ctx();
+        let descendant = WidgetId::reserved(2);
+        ctx.id_path = vec![ViewId::new(1)];
+        ctx.widget_map
+            .insert(descendant, (None, vec![ViewId::new(1), ViewId::new(2)]));
+
+        let mut harness = TestHarness::create(WidgetPod::new(Label::new("a")));
+        harness.edit_root_widget(|root| {
+            ctx.cache_keep_alive(view::KeepAliveKey::new("k"), root, ());
+        });
+
+        for _ in 0..KEEP_ALIVE_EVICTION_PASSES {
+            ctx.evict_stale_keep_alive();
+        }
+
+        assert!(!ctx
+            .keep_alive_map
+            .contains_key(&view::KeepAliveKey::new("k")));
+        assert!(
+            !ctx.widget_map.contains_key(&descendant),
+            "an evicted subtree's widgets must not linger in widget_map forever"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A serializable mutation stream describing changes to a Xilem-driven widget tree,
+//! for mirroring it to (or driving it from) another process — the technique behind
+//! Dioxus LiveView's binary edit protocol.
+//!
+//! [`ViewCtx::start_recording`] switches a context into recording mode, in which
+//! [`ViewCtx::with_action_widget`] and [`MasonryDriver::on_action`](crate::MasonryDriver)
+//! append to a [`MutationRecorder`] instead of (or alongside) mutating a local widget
+//! tree directly; [`ViewCtx::stop_recording`] hands back the ordered [`Mutation`]s to
+//! send across whatever transport the application chooses.
+//!
+//! Only [`Mutation::CreateWidget`] and [`Mutation::RouteAction`] are emitted
+//! automatically today, from the two points `ViewCtx`/[`MasonryDriver`](crate::MasonryDriver)
+//! already funnel action widgets and primary-window actions through; actions from a
+//! secondary window and focus changes are not recorded. [`Mutation::SetProperty`] is each
+//! view's own responsibility, since only it knows which of its properties changed
+//! (see the `label` view for the pattern); [`Mutation::InsertChild`],
+//! [`Mutation::RemoveChild`] and [`Mutation::ReplaceWith`] are likewise for a
+//! container view to emit when it restructures its own children — none of the views
+//! in this crate do that yet, so nothing emits them, but [`ViewCtx::record_mutation`]
+//! is `pub` for one that does.
+//!
+//! [`Mutation`]'s derive below requires `masonry::WidgetId` and `xilem_core::ViewId`
+//! to implement `serde`'s `Serialize`/`Deserialize`; [`assert_wire_ids_are_serde`]
+//! pins a failure of that requirement to one clear spot instead of the wall of
+//! unrelated-looking errors a derive failure on a multi-field enum produces.
+
+use masonry::WidgetId;
+use serde::{Deserialize, Serialize};
+use xilem_core::ViewId;
+
+/// Fails to compile if `WidgetId`/`ViewId` ever stop implementing `Serialize`/
+/// `Deserialize`, rather than letting [`Mutation`]'s derive fail with errors pointing
+/// at its individual fields instead of the actual missing impl.
+#[allow(dead_code)]
+fn assert_wire_ids_are_serde() {
+    fn assert_serde<T: Serialize + for<'de> Deserialize<'de>>() {}
+    assert_serde::<WidgetId>();
+    assert_serde::<ViewId>();
+}
+
+/// A `masonry::Action`, already encoded by whoever raised it. This crate treats it as
+/// opaque bytes rather than a typed payload, since which actions exist is closed over
+/// Masonry's widget set, not known here.
+pub type EncodedAction = Vec<u8>;
+
+/// A widget property value simple enough to serialize without knowing the concrete
+/// widget type that owns it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PropertyValue {
+    Text(String),
+    Bool(bool),
+    Float(f64),
+}
+
+/// One change to a widget tree, carrying only wire-safe identifiers (`WidgetId`s and
+/// `ViewId` paths) and values, so it can be serialized, sent across a process
+/// boundary, and replayed by
+/// [`MasonryDriver::apply_mutations`](crate::MasonryDriver::apply_mutations) against
+/// an independently-reconstructed tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Mutation {
+    /// A widget of Masonry type `type_name` was created as `widget`.
+    CreateWidget { widget: WidgetId, type_name: String },
+    /// `property` on `widget` was set to `value`.
+    SetProperty {
+        widget: WidgetId,
+        property: &'static str,
+        value: PropertyValue,
+    },
+    /// `child` became `parent`'s child at `index`.
+    InsertChild {
+        parent: WidgetId,
+        index: usize,
+        child: WidgetId,
+    },
+    /// `child` is no longer one of `parent`'s children.
+    RemoveChild { parent: WidgetId, child: WidgetId },
+    /// `old` was replaced by `new` at the position `old` occupied.
+    ReplaceWith { old: WidgetId, new: WidgetId },
+    /// `widget` raised an action, to be routed to the view built at `path`.
+    RouteAction {
+        widget: WidgetId,
+        path: Vec<ViewId>,
+        action: EncodedAction,
+    },
+}
+
+impl Mutation {
+    /// Encodes this mutation as a compact binary blob, for sending across a
+    /// transport such as a `WebSocket`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("`Mutation` only contains serializable fields")
+    }
+
+    /// Decodes a [`Mutation`] previously produced by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        bincode::deserialize(bytes)
+    }
+}
+
+/// The ordered list of [`Mutation`]s a `build`/`rebuild` pass has made so far, while a
+/// [`ViewCtx`](crate::ViewCtx) is in recording mode.
+#[derive(Debug, Default)]
+pub struct MutationRecorder {
+    mutations: Vec<Mutation>,
+}
+
+impl MutationRecorder {
+    pub(crate) fn push(&mut self, mutation: Mutation) {
+        self.mutations.push(mutation);
+    }
+
+    /// Takes every mutation recorded so far, for sending across the wire.
+    pub fn drain(&mut self) -> Vec<Mutation> {
+        std::mem::take(&mut self.mutations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ViewHarness;
+    use crate::view::label;
+
+    #[test]
+    fn mutation_round_trips_through_bytes() {
+        let mutation = Mutation::RouteAction {
+            widget: WidgetId::reserved(1),
+            path: vec![ViewId::new(1), ViewId::new(2)],
+            action: vec![1, 2, 3],
+        };
+
+        let decoded = Mutation::from_bytes(&mutation.to_bytes()).expect("valid bytes decode");
+
+        assert!(matches!(
+            decoded,
+            Mutation::RouteAction { widget, path, action }
+                if widget == WidgetId::reserved(1)
+                    && path == vec![ViewId::new(1), ViewId::new(2)]
+                    && action == vec![1, 2, 3]
+        ));
+    }
+
+    #[test]
+    fn opening_a_window_records_create_widget() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        use winit::window::WindowAttributes;
+
+        use crate::view::{window, windows};
+        use crate::WidgetView;
+
+        let open_second = Rc::new(Cell::new(false));
+        let flag = open_second.clone();
+        let mut harness = ViewHarness::new((), move |_| {
+            let mut list = vec![window(
+                WindowAttributes::default(),
+                label("primary").boxed(),
+            )];
+            if flag.get() {
+                list.push(window(
+                    WindowAttributes::default(),
+                    label("secondary").boxed(),
+                ));
+            }
+            windows(list)
+        });
+
+        harness.start_recording();
+        open_second.set(true);
+        harness.rebuild();
+        let mutations = harness.stop_recording();
+
+        assert!(mutations
+            .iter()
+            .any(|mutation| matches!(mutation, Mutation::CreateWidget { .. })));
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A headless harness for driving [`Xilem`](crate::Xilem) views in tests, without
+//! opening a window.
+//!
+//! [`ViewHarness`] plays the same role for views that Masonry's own
+//! `masonry::testing::TestHarness` plays for widgets: it builds a view's widget tree,
+//! lets you assert on it, inject messages as if a widget had raised an action, pump
+//! queued async work, and re-run `logic` to exercise rebuild/diffing — all without a
+//! winit event loop.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use masonry::testing::TestHarness;
+use xilem_core::{DynMessage, MessageResult, RawProxy, ViewId};
+
+use crate::{Mutation, Pod, ViewCtx, WidgetMap, WidgetView};
+
+/// A message an [`async_action`](crate::async_action) or other background work asked
+/// to be delivered to the view at `path`.
+struct QueuedMessage {
+    path: Vec<ViewId>,
+    message: DynMessage,
+}
+
+/// A stub [`RawProxy`] used in tests: instead of waking a winit event loop, it queues
+/// messages for [`ViewHarness::pump_async`] to drain on demand.
+#[derive(Clone, Default)]
+struct RecordingProxy(Arc<Mutex<VecDeque<QueuedMessage>>>);
+
+impl RawProxy for RecordingProxy {
+    fn send_message(&self, path: Vec<ViewId>, message: DynMessage) {
+        self.0
+            .lock()
+            .unwrap()
+            .push_back(QueuedMessage { path, message });
+    }
+
+    fn dyn_clone(&self) -> Arc<dyn RawProxy> {
+        Arc::new(self.clone())
+    }
+}
+
+/// Drives a single [`WidgetView`] through build/rebuild/message without ever opening
+/// a window.
+///
+/// ```
+/// use xilem::testing::ViewHarness;
+/// use xilem::view::label;
+///
+/// let mut harness = ViewHarness::new((), |()| label("hello"));
+/// harness.rebuild();
+/// ```
+pub struct ViewHarness<State, Logic, View: WidgetView<State>> {
+    state: State,
+    logic: Logic,
+    ctx: ViewCtx,
+    current_view: View,
+    view_state: View::ViewState,
+    widgets: TestHarness,
+    proxy: RecordingProxy,
+}
+
+impl<State, Logic, View> ViewHarness<State, Logic, View>
+where
+    View: WidgetView<State>,
+    Logic: FnMut(&mut State) -> View,
+{
+    /// Runs `logic` once and builds the resulting view into a headless widget tree.
+    pub fn new(mut state: State, mut logic: Logic) -> Self {
+        let proxy = RecordingProxy::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("building a current-thread tokio runtime");
+        let mut ctx = ViewCtx {
+            widget_map: WidgetMap::default(),
+            id_path: Vec::new(),
+            view_tree_changed: false,
+            proxy: Arc::new(proxy.clone()),
+            runtime,
+            current_window: None,
+            secondary_widgets: Default::default(),
+            next_secondary_slot: 0,
+            window_requests: Vec::new(),
+            keep_alive_map: Default::default(),
+            focus_scope_stack: Vec::new(),
+            focus_registry: Default::default(),
+            next_focus_scope: 0,
+            focus_requests: Vec::new(),
+            recorder: None,
+        };
+        let current_view = logic(&mut state);
+        let (pod, view_state) = current_view.build(&mut ctx);
+        let widgets = TestHarness::create(pod.inner);
+        Self {
+            state,
+            logic,
+            ctx,
+            current_view,
+            view_state,
+            widgets,
+            proxy,
+        }
+    }
+
+    /// The widget tree `logic` has produced so far.
+    pub fn widget(&self) -> &View::Widget {
+        self.widgets.root_widget()
+    }
+
+    /// Re-runs `logic` and rebuilds the widget tree to match, exercising
+    /// `View::rebuild` against the previous view exactly as
+    /// [`MasonryDriver`](crate::MasonryDriver) would.
+    pub fn rebuild(&mut self) {
+        let next_view = (self.logic)(&mut self.state);
+        self.widgets.edit_root_widget(|root| {
+            next_view.rebuild(
+                &self.current_view,
+                &mut self.view_state,
+                &mut self.ctx,
+                root,
+            );
+        });
+        self.current_view = next_view;
+    }
+
+    /// Delivers `message` to the view at `id_path`, as if the widget there had raised
+    /// an action, returning what `View::message` reported.
+    pub fn message_at(&mut self, id_path: &[ViewId], message: DynMessage) -> MessageResult<()> {
+        self.current_view
+            .message(&mut self.view_state, id_path, message, &mut self.state)
+    }
+
+    /// Starts recording every [`Mutation`] this harness's view tree produces from
+    /// here on; see [`ViewCtx::start_recording`].
+    pub fn start_recording(&mut self) {
+        self.ctx.start_recording();
+    }
+
+    /// Stops recording and returns every [`Mutation`] produced since the matching
+    /// [`Self::start_recording`] call; see [`ViewCtx::stop_recording`].
+    pub fn stop_recording(&mut self) -> Vec<Mutation> {
+        self.ctx.stop_recording()
+    }
+
+    /// Gives the tasks spawned on the harness's current-thread runtime one turn to
+    /// run, then drains every message queued via [`async_action`](crate::async_action)
+    /// (or other background work) so far, delivering each and rebuilding once at the
+    /// end.
+    pub fn pump_async(&mut self) {
+        self.ctx.runtime().block_on(tokio::task::yield_now());
+        let queued: Vec<_> = self.proxy.0.lock().unwrap().drain(..).collect();
+        if queued.is_empty() {
+            return;
+        }
+        for queued in queued {
+            self.message_at(&queued.path, queued.message);
+        }
+        self.rebuild();
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Keyboard focus management: [`focus_scope`] delimits a Tab-cycle boundary, and
+//! [`focusable`] registers a widget within its innermost enclosing scope's focus
+//! ring.
+
+use std::marker::PhantomData;
+
+use masonry::WidgetId;
+use xilem_core::{DynMessage, MessageResult, Mut, View, ViewId, ViewPathTracker};
+
+use crate::{ViewCtx, WidgetView};
+
+/// Identifies one [`focus_scope`], stable for as long as it stays at the same
+/// position in the view tree.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FocusScopeId(pub(crate) u64);
+
+/// A request to imperatively move keyboard focus, applied by the driver on its next
+/// pass (mirroring how widget mutations are always applied between rebuilds, never
+/// mid-build).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusRequest {
+    /// Move to the next focusable widget in the innermost scope, wrapping at its end.
+    Next,
+    /// Move to the previous focusable widget in the innermost scope, wrapping at its
+    /// start.
+    Previous,
+    /// Move focus directly to a specific widget.
+    Widget(WidgetId),
+}
+
+/// The message delivered to a [`focusable`] view when its widget gains or loses
+/// keyboard focus.
+pub(crate) struct FocusChanged(pub(crate) bool);
+
+/// Delimits a keyboard Tab-cycle boundary: Tab/Shift-Tab move focus only among the
+/// [`focusable`] widgets nested inside `child`, wrapping at its edges rather than
+/// escaping into an enclosing scope.
+pub fn focus_scope<V>(child: V) -> FocusScope<V> {
+    FocusScope { child }
+}
+
+/// The [`View`] created by [`focus_scope`].
+pub struct FocusScope<V> {
+    child: V,
+}
+
+impl<State, Action, V> View<State, Action, ViewCtx> for FocusScope<V>
+where
+    V: WidgetView<State, Action>,
+{
+    type Element = V::Element;
+    type ViewState = (FocusScopeId, V::ViewState);
+
+    fn build(&self, ctx: &mut ViewCtx) -> (Self::Element, Self::ViewState) {
+        let scope = ctx.push_focus_scope();
+        let (element, view_state) = self.child.build(ctx);
+        ctx.pop_focus_scope();
+        (element, (scope, view_state))
+    }
+
+    fn rebuild(
+        &self,
+        prev: &Self,
+        (scope, view_state): &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        element: Mut<Self::Element>,
+    ) {
+        ctx.reenter_focus_scope(*scope);
+        self.child.rebuild(&prev.child, view_state, ctx, element);
+        ctx.pop_focus_scope();
+    }
+
+    fn teardown(
+        &self,
+        (scope, view_state): &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        element: Mut<Self::Element>,
+    ) {
+        self.child.teardown(view_state, ctx, element);
+        ctx.remove_focus_scope(*scope);
+    }
+
+    fn message(
+        &self,
+        (_, view_state): &mut Self::ViewState,
+        id_path: &[ViewId],
+        message: DynMessage,
+        app_state: &mut State,
+    ) -> MessageResult<Action> {
+        self.child.message(view_state, id_path, message, app_state)
+    }
+}
+
+/// The id `focusable` pushes for its single child, letting `message`
+/// tell an event meant for this wrapper (an empty remaining path) from one meant for
+/// the child (a path starting with this id).
+const CHILD_ID: ViewId = ViewId::new(0);
+
+/// Registers `child`'s widget in its innermost enclosing [`focus_scope`]'s focus
+/// ring, and reports focus gained/lost to `on_focus_changed`.
+pub fn focusable<State, Action, V, F>(child: V, on_focus_changed: F) -> Focusable<State, V, F>
+where
+    V: WidgetView<State, Action>,
+    F: Fn(&mut State, bool) -> Action + Send + Sync + 'static,
+{
+    Focusable {
+        child,
+        on_focus_changed,
+        state: PhantomData,
+    }
+}
+
+/// The [`View`] created by [`focusable`].
+pub struct Focusable<State, V, F> {
+    child: V,
+    on_focus_changed: F,
+    state: PhantomData<fn(&mut State)>,
+}
+
+impl<State, Action, V, F> View<State, Action, ViewCtx> for Focusable<State, V, F>
+where
+    V: WidgetView<State, Action>,
+    F: Fn(&mut State, bool) -> Action + Send + Sync + 'static,
+{
+    type Element = V::Element;
+    type ViewState = V::ViewState;
+
+    fn build(&self, ctx: &mut ViewCtx) -> (Self::Element, Self::ViewState) {
+        ctx.push_id(CHILD_ID);
+        let (pod, view_state) = self.child.build(ctx);
+        ctx.pop_id();
+        ctx.register_focusable(pod.inner.id());
+        (pod, view_state)
+    }
+
+    fn rebuild(
+        &self,
+        prev: &Self,
+        view_state: &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        element: Mut<Self::Element>,
+    ) {
+        let widget_id = element.ctx.widget_id();
+        ctx.push_id(CHILD_ID);
+        self.child.rebuild(&prev.child, view_state, ctx, element);
+        ctx.pop_id();
+        // Re-registering keeps this widget's place in the ring even if focusable
+        // siblings before it were added or removed this rebuild pass.
+        ctx.register_focusable(widget_id);
+    }
+
+    fn teardown(
+        &self,
+        view_state: &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        element: Mut<Self::Element>,
+    ) {
+        ctx.push_id(CHILD_ID);
+        self.child.teardown(view_state, ctx, element);
+        ctx.pop_id();
+    }
+
+    fn message(
+        &self,
+        view_state: &mut Self::ViewState,
+        id_path: &[ViewId],
+        message: DynMessage,
+        app_state: &mut State,
+    ) -> MessageResult<Action> {
+        // `FocusChanged` is dispatched by the driver using the same recorded
+        // `id_path` as a regular widget action from this same widget (both come
+        // from `ViewCtx`'s widget map), so that path always starts with `CHILD_ID`
+        // here too. Check the message type first, rather than `id_path`, so this
+        // doesn't always get forwarded to `self.child` before we ever see it.
+        let message = match message.downcast::<FocusChanged>() {
+            Ok(FocusChanged(has_focus)) => {
+                return MessageResult::Action((self.on_focus_changed)(app_state, has_focus));
+            }
+            Err(message) => message,
+        };
+        match id_path.split_first() {
+            Some((&CHILD_ID, rest)) => self.child.message(view_state, rest, message, app_state),
+            _ => MessageResult::Stale(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ViewHarness;
+    use crate::view::label;
+
+    #[test]
+    fn focus_changed_is_routed_to_on_focus_changed() {
+        let mut harness = ViewHarness::new(false, |_| {
+            focusable(label("a"), |state: &mut bool, has_focus| {
+                *state = has_focus;
+            })
+        });
+
+        let result = harness.message_at(&[CHILD_ID], Box::new(FocusChanged(true)));
+
+        assert!(matches!(result, MessageResult::Action(())));
+    }
+
+    #[test]
+    fn message_with_unrelated_id_path_is_stale() {
+        let mut harness = ViewHarness::new(false, |_| {
+            focusable(label("a"), |state: &mut bool, has_focus| *state = has_focus)
+        });
+        // Any message other than `FocusChanged`, which is handled before the path is
+        // looked at.
+        let result = harness.message_at(&[ViewId::new(1)], Box::new(()));
+
+        assert!(matches!(result, MessageResult::Stale(_)));
+    }
+}
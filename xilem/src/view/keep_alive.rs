@@ -0,0 +1,101 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`keep_alive`], a view wrapper that preserves a child's widget subtree across
+//! rebuilds in which it temporarily disappears from the view tree, rather than
+//! tearing it down and rebuilding it from scratch if it reappears.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use xilem_core::{DynMessage, MessageResult, Mut, View, ViewId};
+
+use crate::{ViewCtx, WidgetView};
+
+/// A stable identity for a [`keep_alive`]d subtree, independent of its position in
+/// the view tree.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeepAliveKey(u64);
+
+impl KeepAliveKey {
+    /// Derives a key from any [`Hash`] value, e.g. a document id or tab index.
+    pub fn new(key: impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// Preserves `child`'s built widget and view state across rebuilds in which it
+/// disappears from the view tree (e.g. a closed-then-reopened tab), keyed by `key`.
+///
+/// Reviving a cached entry skips `child`'s `build` entirely, so widget-internal
+/// state such as scroll position, a text field's contents, or animation progress
+/// survives the round trip. Entries which go unrevived for a few rebuild passes are
+/// dropped, so the cache does not grow without bound; see
+/// [`ViewCtx::evict_stale_keep_alive`](crate::ViewCtx).
+pub fn keep_alive<V>(key: impl Hash, child: V) -> KeepAlive<V> {
+    KeepAlive {
+        key: KeepAliveKey::new(key),
+        child,
+    }
+}
+
+/// The [`View`] created by [`keep_alive`].
+pub struct KeepAlive<V> {
+    key: KeepAliveKey,
+    child: V,
+}
+
+impl<State, Action, V> View<State, Action, ViewCtx> for KeepAlive<V>
+where
+    V: WidgetView<State, Action>,
+    V::ViewState: Default + 'static,
+{
+    type Element = V::Element;
+    type ViewState = V::ViewState;
+
+    fn build(&self, ctx: &mut ViewCtx) -> (Self::Element, Self::ViewState) {
+        if let Some(revived) = ctx.revive_keep_alive(self.key) {
+            return revived;
+        }
+        self.child.build(ctx)
+    }
+
+    fn rebuild(
+        &self,
+        prev: &Self,
+        view_state: &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        element: Mut<Self::Element>,
+    ) {
+        debug_assert!(
+            prev.key == self.key,
+            "`keep_alive`'s key must stay the same across a rebuild; give the new key \
+             its own `keep_alive` call instead of changing this one's"
+        );
+        self.child.rebuild(&prev.child, view_state, ctx, element);
+    }
+
+    fn teardown(
+        &self,
+        view_state: &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        element: Mut<Self::Element>,
+    ) {
+        // Rather than tearing the widget down (`ctx.teardown_leaf`/recursive
+        // teardown), stash it and the logical state built up so far, in case this
+        // key's content reappears in a later rebuild.
+        ctx.cache_keep_alive(self.key, element, std::mem::take(view_state));
+    }
+
+    fn message(
+        &self,
+        view_state: &mut Self::ViewState,
+        id_path: &[ViewId],
+        message: DynMessage,
+        app_state: &mut State,
+    ) -> MessageResult<Action> {
+        self.child.message(view_state, id_path, message, app_state)
+    }
+}
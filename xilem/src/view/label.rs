@@ -0,0 +1,88 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+use masonry::text::ArcStr;
+use masonry::widget;
+use xilem_core::{DynMessage, MessageResult, Mut, View, ViewId};
+
+use crate::{Color, Mutation, Pod, PropertyValue, TextAlignment, ViewCtx};
+
+/// Shows a piece of text, styled as a single label.
+pub fn label(label: impl Into<ArcStr>) -> Label {
+    Label {
+        label: label.into(),
+        text_color: Color::WHITE,
+        alignment: TextAlignment::default(),
+    }
+}
+
+/// The [`View`] created by [`label`].
+pub struct Label {
+    label: ArcStr,
+    text_color: Color,
+    alignment: TextAlignment,
+}
+
+impl Label {
+    /// Sets the text color.
+    pub fn color(mut self, color: Color) -> Self {
+        self.text_color = color;
+        self
+    }
+
+    /// Sets the text alignment.
+    pub fn alignment(mut self, alignment: TextAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+}
+
+impl<State, Action> View<State, Action, ViewCtx> for Label {
+    type Element = Pod<widget::Label>;
+    type ViewState = ();
+
+    fn build(&self, ctx: &mut ViewCtx) -> (Self::Element, Self::ViewState) {
+        let widget = widget::Label::new(self.label.clone())
+            .with_text_color(self.text_color)
+            .with_alignment(self.alignment);
+        (ctx.with_leaf_action_widget(|_| Pod::new(widget)).0, ())
+    }
+
+    fn rebuild(
+        &self,
+        prev: &Self,
+        (): &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        mut element: Mut<Self::Element>,
+    ) {
+        let widget = element.ctx.widget_id();
+        if prev.label != self.label {
+            widget::Label::set_text(&mut element, self.label.clone());
+            ctx.record_mutation(Mutation::SetProperty {
+                widget,
+                property: "text",
+                value: PropertyValue::Text(self.label.to_string()),
+            });
+        }
+        if prev.text_color != self.text_color {
+            widget::Label::set_text_color(&mut element, self.text_color);
+        }
+        if prev.alignment != self.alignment {
+            widget::Label::set_alignment(&mut element, self.alignment);
+        }
+    }
+
+    fn teardown(&self, (): &mut Self::ViewState, ctx: &mut ViewCtx, element: Mut<Self::Element>) {
+        ctx.teardown_leaf(element);
+    }
+
+    fn message(
+        &self,
+        (): &mut Self::ViewState,
+        _id_path: &[ViewId],
+        message: DynMessage,
+        _app_state: &mut State,
+    ) -> MessageResult<Action> {
+        MessageResult::Stale(message)
+    }
+}
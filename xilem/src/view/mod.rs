@@ -0,0 +1,25 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Views, the widget equivalent in Xilem.
+//!
+//! This module contains the built-in [`View`](xilem_core::View) implementations
+//! Xilem provides, which are mostly thin wrappers over the Masonry widgets of the
+//! same name.
+
+mod label;
+pub use label::{label, Label};
+
+mod window;
+pub use window::{window, windows, Window, Windows, WindowsState};
+
+mod keep_alive;
+pub use keep_alive::{keep_alive, KeepAlive, KeepAliveKey};
+
+// `pub(crate)`, rather than private, so `crate::driver` can reach `focus::FocusChanged`
+// and route it the same way it routes a regular widget action.
+pub(crate) mod focus;
+pub use focus::{focus_scope, focusable, FocusRequest, FocusScope, FocusScopeId, Focusable};
+
+pub mod resource;
+pub use resource::{resource, Resource, ResourceState, ResourceView};
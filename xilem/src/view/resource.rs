@@ -0,0 +1,287 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`resource`], a view that fetches async data keyed by a dependency value, caching
+//! the result across rebuilds and re-fetching only when the dependency changes.
+
+use std::future::Future;
+use std::hash::Hash;
+
+use tokio::task::JoinHandle;
+use xilem_core::{
+    AsyncCtx, DynMessage, MessageResult, Mut, NoElement, View, ViewId, ViewPathTracker,
+};
+
+use crate::ViewCtx;
+
+/// The state of a [`resource`] at a point in time.
+pub enum Resource<T, E> {
+    /// The future spawned for the current dependency value hasn't resolved yet.
+    Pending,
+    /// The future resolved successfully.
+    Ok(T),
+    /// The future resolved with an error.
+    Err(E),
+}
+
+/// A state transition a [`resource`] announces back through
+/// [`RawProxy`](xilem_core::RawProxy): `Pending` is sent synchronously from
+/// `build`/`rebuild` the moment a fetch starts (those have no access to `State` to
+/// call `on_update` directly, so routing it through a message is the only way to
+/// deliver it), `Completed` once the spawned task resolves. `generation` lets
+/// [`ResourceView::message`] drop either one that belongs to a fetch a later
+/// `rebuild` has since superseded.
+enum ResourceEvent<T, E> {
+    Pending {
+        generation: u64,
+    },
+    Completed {
+        generation: u64,
+        result: Result<T, E>,
+    },
+}
+
+/// Fetches async data depending on `deps`, re-running `future` only when `deps`
+/// changes, and caching the last resolved value in between.
+///
+/// `future` is called again, and the previous attempt's [`JoinHandle`] aborted,
+/// every time `deps` compares unequal to the value from the previous build/rebuild;
+/// an unchanged `deps` leaves the cached value and any still-running task alone.
+/// `on_update` is invoked with the new [`Resource`] state — `Pending`, delivered
+/// through a same-tick message the moment a fetch starts (so it reaches `State` on
+/// the next pass through `logic`, not synchronously within this rebuild), then
+/// `Ok`/`Err` once the future resolves — letting `logic` fold it into `State`.
+///
+/// ```ignore
+/// resource(user_id, move || async move { fetch_user(user_id).await }, |state, result| {
+///     state.user = match result {
+///         Resource::Ok(user) => Some(user.clone()),
+///         _ => None,
+///     };
+/// })
+/// ```
+pub fn resource<State, Action, Deps, F, Fut, T, E>(
+    deps: Deps,
+    future: F,
+    on_update: impl Fn(&mut State, &Resource<T, E>) -> Action + Send + Sync + 'static,
+) -> ResourceView<State, Action, Deps, F, T, E>
+where
+    Deps: Hash + Eq + Clone + Send + 'static,
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    ResourceView {
+        deps,
+        future,
+        on_update: Box::new(on_update),
+    }
+}
+
+/// The [`View`] created by [`resource`].
+pub struct ResourceView<State, Action, Deps, F, T, E> {
+    deps: Deps,
+    future: F,
+    on_update: Box<dyn Fn(&mut State, &Resource<T, E>) -> Action + Send + Sync>,
+}
+
+/// The [`View::ViewState`] of a [`resource`].
+pub struct ResourceState<Deps, T, E> {
+    deps: Deps,
+    value: Resource<T, E>,
+    handle: JoinHandle<()>,
+    generation: u64,
+}
+
+impl<State, Action, Deps, F, Fut, T, E> View<State, Action, ViewCtx>
+    for ResourceView<State, Action, Deps, F, T, E>
+where
+    State: 'static,
+    Action: 'static,
+    Deps: Hash + Eq + Clone + Send + 'static,
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    type Element = NoElement;
+    type ViewState = ResourceState<Deps, T, E>;
+
+    fn build(&self, ctx: &mut ViewCtx) -> (Self::Element, Self::ViewState) {
+        let handle = spawn(ctx, &self.future, 0);
+        (
+            NoElement,
+            ResourceState {
+                deps: self.deps.clone(),
+                value: Resource::Pending,
+                handle,
+                generation: 0,
+            },
+        )
+    }
+
+    fn rebuild(
+        &self,
+        prev: &Self,
+        view_state: &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        _element: Mut<Self::Element>,
+    ) {
+        let _ = prev;
+        if self.deps == view_state.deps {
+            // Same dependency value: keep the cached value and the running task,
+            // rather than cancelling and re-fetching work already in flight.
+            return;
+        }
+        view_state.handle.abort();
+        view_state.deps = self.deps.clone();
+        view_state.value = Resource::Pending;
+        view_state.generation += 1;
+        view_state.handle = spawn(ctx, &self.future, view_state.generation);
+    }
+
+    fn teardown(
+        &self,
+        view_state: &mut Self::ViewState,
+        _ctx: &mut ViewCtx,
+        _element: Mut<Self::Element>,
+    ) {
+        view_state.handle.abort();
+    }
+
+    fn message(
+        &self,
+        view_state: &mut Self::ViewState,
+        _id_path: &[ViewId],
+        message: DynMessage,
+        app_state: &mut State,
+    ) -> MessageResult<Action> {
+        match message.downcast::<ResourceEvent<T, E>>() {
+            Ok(event) => {
+                let generation = match &*event {
+                    ResourceEvent::Pending { generation }
+                    | ResourceEvent::Completed { generation, .. } => *generation,
+                };
+                if generation != view_state.generation {
+                    // An event from a `deps` value `rebuild` has since superseded; the
+                    // task that sent it should already have been aborted, but an event
+                    // can still be in flight when that happens, so drop it rather than
+                    // stomp on newer state.
+                    return MessageResult::Nop;
+                }
+                view_state.value = match *event {
+                    ResourceEvent::Pending { .. } => Resource::Pending,
+                    ResourceEvent::Completed { result, .. } => match result {
+                        Result::Ok(value) => Resource::Ok(value),
+                        Result::Err(error) => Resource::Err(error),
+                    },
+                };
+                MessageResult::Action((self.on_update)(app_state, &view_state.value))
+            }
+            Err(message) => MessageResult::Stale(message),
+        }
+    }
+}
+
+/// Spawns `future` on `ctx`'s tokio runtime, announcing [`ResourceEvent::Pending`]
+/// immediately and delivering the eventual result back through the proxy as a
+/// [`ResourceEvent::Completed`], both tagged with `generation`.
+fn spawn<F, Fut, T, E>(ctx: &mut ViewCtx, future: &F, generation: u64) -> JoinHandle<()>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    let proxy = ctx.proxy();
+    let path = ctx.view_path().to_vec();
+    proxy.send_message(
+        path.clone(),
+        Box::new(ResourceEvent::<T, E>::Pending { generation }),
+    );
+    let future = future();
+    ctx.runtime().spawn(async move {
+        let result = future.await;
+        proxy.send_message(
+            path,
+            Box::new(ResourceEvent::Completed { generation, result }),
+        );
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_ctx;
+
+    #[test]
+    fn message_with_current_generation_resolves_the_value() {
+        let mut ctx = test_ctx();
+        let view = resource(
+            1u32,
+            || async { Ok::<i32, ()>(42) },
+            |_: &mut (), result: &Resource<i32, ()>| matches!(result, Resource::Ok(_)),
+        );
+        let (_element, mut view_state) = view.build(&mut ctx);
+        assert!(matches!(view_state.value, Resource::Pending));
+
+        let mut state = ();
+        let result = view.message(
+            &mut view_state,
+            &[],
+            Box::new(ResourceEvent::Completed {
+                generation: 0,
+                result: Ok::<i32, ()>(42),
+            }),
+            &mut state,
+        );
+
+        assert!(matches!(result, MessageResult::Action(true)));
+        assert!(matches!(view_state.value, Resource::Ok(42)));
+    }
+
+    #[test]
+    fn pending_event_with_current_generation_reaches_on_update() {
+        let mut ctx = test_ctx();
+        let view = resource(
+            1u32,
+            || async { Ok::<i32, ()>(42) },
+            |_: &mut (), result: &Resource<i32, ()>| matches!(result, Resource::Pending),
+        );
+        let (_element, mut view_state) = view.build(&mut ctx);
+
+        let mut state = ();
+        let result = view.message(
+            &mut view_state,
+            &[],
+            Box::new(ResourceEvent::<i32, ()>::Pending { generation: 0 }),
+            &mut state,
+        );
+
+        assert!(matches!(result, MessageResult::Action(true)));
+        assert!(matches!(view_state.value, Resource::Pending));
+    }
+
+    #[test]
+    fn message_from_a_superseded_generation_is_dropped() {
+        let mut ctx = test_ctx();
+        let view = resource(1u32, || async { Ok::<i32, ()>(42) }, |_: &mut (), _| ());
+        let (_element, mut view_state) = view.build(&mut ctx);
+        // A later rebuild changed `deps`, bumping the generation past this completion.
+        view_state.generation = 1;
+
+        let mut state = ();
+        let result = view.message(
+            &mut view_state,
+            &[],
+            Box::new(ResourceEvent::Completed {
+                generation: 0,
+                result: Ok::<i32, ()>(42),
+            }),
+            &mut state,
+        );
+
+        assert!(matches!(result, MessageResult::Nop));
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2024 the Xilem Authors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Windows as views: [`window`] describes one OS window, and [`windows`] lets a
+//! `logic` closure return a dynamic set of them instead of Xilem assuming a single
+//! always-open window.
+
+use std::any::Any;
+use std::marker::PhantomData;
+
+use masonry::widget::RootWidget;
+use winit::window::WindowAttributes;
+use xilem_core::{DynMessage, MessageResult, Mut, View, ViewId};
+
+use crate::{AnyWidgetView, Pod, ViewCtx};
+
+/// Describes one OS window: the [`WindowAttributes`] winit should (re-)apply to it,
+/// and the view tree to show as its content.
+///
+/// A window's identity across rebuilds is its position in the `Vec` passed to
+/// [`windows`]; reordering two windows is not seen as a move: each position keeps its
+/// OS window, and has its attributes and content rebuilt to match the new entry.
+pub struct Window<State, Action = ()> {
+    pub(crate) attributes: WindowAttributes,
+    pub(crate) child: Box<AnyWidgetView<State, Action>>,
+}
+
+/// Creates a [`Window`] with the given `attributes`, showing `child` as its content.
+pub fn window<State: 'static, Action: 'static>(
+    attributes: WindowAttributes,
+    child: impl Into<Box<AnyWidgetView<State, Action>>>,
+) -> Window<State, Action> {
+    Window {
+        attributes,
+        child: child.into(),
+    }
+}
+
+/// The view returned by a `logic` closure which wants to control more than Xilem's
+/// implicit single window: see [`windows`].
+pub struct Windows<State, Action = ()> {
+    windows: Vec<Window<State, Action>>,
+}
+
+/// Lets `logic` describe the whole set of currently-open windows, rather than a
+/// single window's content.
+///
+/// The first entry is used to bootstrap the winit event loop, exactly as a plain
+/// [`WidgetView`](crate::WidgetView) would have been before this existed. Any further
+/// entries are opened via [`ViewCtx::open_window`] during `build`/`rebuild`; entries
+/// present in a previous rebuild but missing from this one are closed.
+///
+/// # Panics
+///
+/// Panics in `build`, and in every later `rebuild`, if `windows` is empty: there must
+/// always be at least one window to host the application, so `logic` returning zero
+/// windows on some later call (a state bug, not just an invalid one-time construction)
+/// brings down the app rather than leaving it in a window-less, undiagnosed limbo.
+pub fn windows<State: 'static, Action: 'static>(
+    windows: Vec<Window<State, Action>>,
+) -> Windows<State, Action> {
+    Windows { windows }
+}
+
+/// The [`View::ViewState`] of [`Windows`]: the primary window's state, plus one
+/// `(slot, state)` entry per secondary window, in the same order they appear in
+/// `Windows::windows[1..]`. `slot` is the one `ViewCtx::reserve_secondary_slot`
+/// assigned it before it was handed to [`ViewCtx::open_window`],
+/// which is what lets [`Windows::rebuild`] reach that window's live content again.
+pub struct WindowsState<State, Action> {
+    primary: Box<dyn Any>,
+    secondary: Vec<(usize, Box<dyn Any>)>,
+    _marker: PhantomData<fn(&mut State) -> Action>,
+}
+
+impl<State: 'static, Action: 'static> View<State, Action, ViewCtx> for Windows<State, Action> {
+    type Element = Pod<RootWidget>;
+    type ViewState = WindowsState<State, Action>;
+
+    fn build(&self, ctx: &mut ViewCtx) -> (Self::Element, Self::ViewState) {
+        let (primary, rest) = self
+            .windows
+            .split_first()
+            .expect("`windows` must describe at least one window");
+        let (pod, primary_state) = primary.child.build(ctx);
+        ctx.update_primary_window(primary.attributes.clone());
+        let mut secondary = Vec::with_capacity(rest.len());
+        for window in rest {
+            let slot = ctx.reserve_secondary_slot();
+            let (child_pod, child_state) =
+                ctx.with_secondary_window(slot, |ctx| window.child.build(ctx));
+            ctx.open_window(slot, window.attributes.clone(), child_pod);
+            secondary.push((slot, child_state));
+        }
+        (
+            Pod::new(RootWidget::from_pod(pod.inner)),
+            WindowsState {
+                primary: primary_state,
+                secondary,
+                _marker: PhantomData,
+            },
+        )
+    }
+
+    fn rebuild(
+        &self,
+        prev: &Self,
+        view_state: &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        mut element: Mut<Self::Element>,
+    ) {
+        let (primary, rest) = self
+            .windows
+            .split_first()
+            .expect("`windows` must describe at least one window");
+        let (prev_primary, prev_rest) = prev
+            .windows
+            .split_first()
+            .expect("`windows` must describe at least one window");
+
+        // The primary window's content lives directly in `element`, the same as any
+        // other view's element; only the secondary windows need `ViewCtx` to reach
+        // their own `RootWidget`s, since those live outside this element's tree.
+        let primary_element = RootWidget::child_mut(&mut element);
+        primary.child.rebuild(
+            &prev_primary.child,
+            &mut view_state.primary,
+            ctx,
+            primary_element,
+        );
+        ctx.update_primary_window(primary.attributes.clone());
+
+        let shared = prev_rest.len().min(rest.len());
+        for (i, next_window) in rest.iter().enumerate().take(shared) {
+            let (slot, child_state) = &mut view_state.secondary[i];
+            ctx.update_window(*slot, next_window.attributes.clone());
+            ctx.with_secondary_root_mut(*slot, |ctx, secondary_element| {
+                next_window
+                    .child
+                    .rebuild(&prev_rest[i].child, child_state, ctx, secondary_element);
+            });
+        }
+        for i in (shared..prev_rest.len()).rev() {
+            let (slot, child_state) = &mut view_state.secondary[i];
+            ctx.with_secondary_root_mut(*slot, |ctx, secondary_element| {
+                prev_rest[i]
+                    .child
+                    .teardown(child_state, ctx, secondary_element);
+            });
+            ctx.close_window(*slot);
+        }
+        view_state.secondary.truncate(shared);
+        for window in &rest[shared..] {
+            let slot = ctx.reserve_secondary_slot();
+            let (child_pod, child_state) =
+                ctx.with_secondary_window(slot, |ctx| window.child.build(ctx));
+            ctx.open_window(slot, window.attributes.clone(), child_pod);
+            view_state.secondary.push((slot, child_state));
+        }
+    }
+
+    fn teardown(
+        &self,
+        view_state: &mut Self::ViewState,
+        ctx: &mut ViewCtx,
+        _element: Mut<Self::Element>,
+    ) {
+        let rest = self.windows.split_first().map_or(&[][..], |(_, rest)| rest);
+        for (window, (slot, mut child_state)) in
+            rest.iter().zip(view_state.secondary.drain(..)).rev()
+        {
+            ctx.with_secondary_root_mut(slot, |ctx, secondary_element| {
+                window
+                    .child
+                    .teardown(&mut child_state, ctx, secondary_element);
+            });
+            ctx.close_window(slot);
+        }
+    }
+
+    fn message(
+        &self,
+        _view_state: &mut Self::ViewState,
+        _id_path: &[ViewId],
+        message: DynMessage,
+        _app_state: &mut State,
+    ) -> MessageResult<Action> {
+        // Routing is handled by the driver directly against each open window's own
+        // view state, since `WindowsState` type-erases per-window state to support a
+        // heterogeneous, dynamically-sized set of windows.
+        MessageResult::Stale(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::testing::ViewHarness;
+    use crate::view::label;
+    use crate::{Mutation, WidgetView};
+
+    #[test]
+    fn rebuild_diffs_both_primary_and_secondary_window_content() {
+        let generation = Rc::new(Cell::new(0u32));
+        let logic_generation = generation.clone();
+        let mut harness = ViewHarness::new((), move |_| {
+            let (primary, secondary) = if logic_generation.get() == 0 {
+                ("primary 0", "secondary 0")
+            } else {
+                ("primary 1", "secondary 1")
+            };
+            windows(vec![
+                window(WindowAttributes::default(), label(primary).boxed()),
+                window(WindowAttributes::default(), label(secondary).boxed()),
+            ])
+        });
+
+        harness.start_recording();
+        generation.set(1);
+        harness.rebuild();
+        let mutations = harness.stop_recording();
+
+        let changed_text_properties = mutations
+            .iter()
+            .filter(|mutation| {
+                matches!(
+                    mutation,
+                    Mutation::SetProperty {
+                        property: "text",
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(
+            changed_text_properties, 2,
+            "both the primary and the secondary window's content must be rebuilt, not just \
+             left untouched"
+        );
+    }
+}